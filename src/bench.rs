@@ -0,0 +1,246 @@
+use std::fs::File;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use bevy::diagnostic::{Diagnostic, DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use crate::config::Cli;
+use crate::{Cube, StressConfig};
+
+/// Cube-axis counts to sweep, each held for `ticks_per_level` physics ticks.
+const DEFAULT_SCHEDULE: [i32; 4] = [5, 10, 15, 20];
+const DEFAULT_TICKS_PER_LEVEL: u32 = 300;
+
+/// Wall-clock time the physics step (`PhysicsSet::Prepare` through `Sync`)
+/// took this tick. `Time<Physics>`'s delta is the fixed *simulation*
+/// timestep, constant regardless of load, so it can't answer "how expensive
+/// was solving this many cubes" the way this can.
+#[derive(Resource, Default)]
+pub struct PhysicsStepTiming {
+    start: Option<Instant>,
+    last: Option<Duration>,
+}
+
+pub fn begin_physics_step_timing(mut timing: ResMut<PhysicsStepTiming>) {
+    timing.start = Some(Instant::now());
+}
+
+pub fn end_physics_step_timing(mut timing: ResMut<PhysicsStepTiming>) {
+    if let Some(start) = timing.start.take() {
+        timing.last = Some(start.elapsed());
+    }
+}
+
+#[derive(Clone, Copy)]
+struct LevelSample {
+    frame_time_ms: f64,
+}
+
+struct LevelResult {
+    cube_axis_amount: i32,
+    cube_count: i32,
+    mean_frame_time_ms: f64,
+    p50_frame_time_ms: f64,
+    p95_frame_time_ms: f64,
+    p99_frame_time_ms: f64,
+    min_fps: f64,
+    physics_step_time_ms: Option<f64>,
+}
+
+/// Drives the sweep-and-record state machine for `--bench` mode. Inserted
+/// alongside `StressConfig` before `setup` runs for the first level.
+#[derive(Resource)]
+pub struct BenchController {
+    schedule: Vec<i32>,
+    ticks_per_level: u32,
+    out_path: PathBuf,
+    level_index: usize,
+    ticks_this_level: u32,
+    samples: Vec<LevelSample>,
+    results: Vec<LevelResult>,
+}
+
+impl BenchController {
+    pub fn new(schedule: Vec<i32>, ticks_per_level: u32, out_path: PathBuf) -> Self {
+        Self {
+            schedule,
+            ticks_per_level,
+            out_path,
+            level_index: 0,
+            ticks_this_level: 0,
+            samples: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn current_cube_axis_amount(&self) -> i32 {
+        self.schedule[self.level_index]
+    }
+}
+
+/// Parsed `--bench` flags; `None` means run the interactive demo as normal.
+pub struct BenchArgs {
+    pub schedule: Vec<i32>,
+    pub ticks_per_level: u32,
+    pub out_path: PathBuf,
+}
+
+impl BenchArgs {
+    /// Returns `None` when `--bench` wasn't passed, so `main` can fall back
+    /// to the interactive demo.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        if !cli.bench {
+            return None;
+        }
+
+        Some(Self {
+            schedule: cli.schedule.clone().unwrap_or_else(|| DEFAULT_SCHEDULE.to_vec()),
+            ticks_per_level: cli.ticks_per_level.unwrap_or(DEFAULT_TICKS_PER_LEVEL),
+            out_path: cli
+                .out
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("bench_report")),
+        })
+    }
+}
+
+/// Samples `FrameTimeDiagnosticsPlugin` every tick, advances the level once
+/// `ticks_per_level` samples have been taken, and writes the report and
+/// exits once the schedule is exhausted.
+pub fn drive_bench(
+    mut controller: ResMut<BenchController>,
+    diagnostics: Res<DiagnosticsStore>,
+    physics_step_timing: Res<PhysicsStepTiming>,
+    mut exit: EventWriter<AppExit>,
+    mut config: ResMut<StressConfig>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cubes: Query<Entity, With<Cube>>,
+) {
+    if let Some(frame_time) = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(Diagnostic::smoothed)
+    {
+        controller.samples.push(LevelSample {
+            frame_time_ms: frame_time,
+        });
+    }
+
+    controller.ticks_this_level += 1;
+    if controller.ticks_this_level < controller.ticks_per_level {
+        return;
+    }
+
+    finish_level(&mut controller, physics_step_timing.last);
+
+    controller.level_index += 1;
+    if controller.level_index >= controller.schedule.len() {
+        write_report(&controller.out_path, &controller.results);
+        exit.send(AppExit::Success);
+        return;
+    }
+
+    controller.ticks_this_level = 0;
+    config.cube_axis_amount = controller.current_cube_axis_amount();
+
+    for cube in &cubes {
+        commands.entity(cube).despawn();
+    }
+    crate::spawn_cube_pile(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        config.cube_axis_amount,
+        config.cube_mass,
+    );
+}
+
+fn finish_level(controller: &mut BenchController, physics_step_time: Option<Duration>) {
+    let mut frame_times: Vec<f64> = controller
+        .samples
+        .drain(..)
+        .map(|s| s.frame_time_ms)
+        .collect();
+    frame_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mean = frame_times.iter().sum::<f64>() / frame_times.len().max(1) as f64;
+    let percentile = |p: f64| -> f64 {
+        if frame_times.is_empty() {
+            return 0.0;
+        }
+        let index = ((frame_times.len() as f64 - 1.0) * p).round() as usize;
+        frame_times[index]
+    };
+    let max_frame_time = frame_times.last().copied().unwrap_or(0.0);
+
+    let cube_axis_amount = controller.current_cube_axis_amount();
+    controller.results.push(LevelResult {
+        cube_axis_amount,
+        cube_count: cube_axis_amount.pow(3),
+        mean_frame_time_ms: mean,
+        p50_frame_time_ms: percentile(0.50),
+        p95_frame_time_ms: percentile(0.95),
+        p99_frame_time_ms: percentile(0.99),
+        min_fps: if max_frame_time > 0.0 {
+            1000.0 / max_frame_time
+        } else {
+            0.0
+        },
+        physics_step_time_ms: physics_step_time.map(|d| d.as_secs_f64() * 1000.0),
+    });
+}
+
+fn write_report(out_path: &std::path::Path, results: &[LevelResult]) {
+    let csv_path = out_path.with_extension("csv");
+    let json_path = out_path.with_extension("json");
+
+    if let Ok(mut csv) = File::create(&csv_path) {
+        let _ = writeln!(
+            csv,
+            "cube_axis_amount,cube_count,mean_frame_time_ms,p50_frame_time_ms,p95_frame_time_ms,p99_frame_time_ms,min_fps,physics_step_time_ms"
+        );
+        for result in results {
+            let _ = writeln!(
+                csv,
+                "{},{},{:.4},{:.4},{:.4},{:.4},{:.2},{}",
+                result.cube_axis_amount,
+                result.cube_count,
+                result.mean_frame_time_ms,
+                result.p50_frame_time_ms,
+                result.p95_frame_time_ms,
+                result.p99_frame_time_ms,
+                result.min_fps,
+                result
+                    .physics_step_time_ms
+                    .map(|v| format!("{v:.4}"))
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    if let Ok(mut json) = File::create(&json_path) {
+        let entries: Vec<String> = results
+            .iter()
+            .map(|result| {
+                format!(
+                    "{{\"cube_axis_amount\":{},\"cube_count\":{},\"mean_frame_time_ms\":{:.4},\"p50_frame_time_ms\":{:.4},\"p95_frame_time_ms\":{:.4},\"p99_frame_time_ms\":{:.4},\"min_fps\":{:.2},\"physics_step_time_ms\":{}}}",
+                    result.cube_axis_amount,
+                    result.cube_count,
+                    result.mean_frame_time_ms,
+                    result.p50_frame_time_ms,
+                    result.p95_frame_time_ms,
+                    result.p99_frame_time_ms,
+                    result.min_fps,
+                    result
+                        .physics_step_time_ms
+                        .map(|v| format!("{v:.4}"))
+                        .unwrap_or_else(|| "null".to_string()),
+                )
+            })
+            .collect();
+        let _ = writeln!(json, "[{}]", entries.join(","));
+    }
+}
@@ -0,0 +1,104 @@
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+const MOUSE_SENSITIVITY: f32 = 0.003;
+const PITCH_LIMIT: f32 = 1.54; // just short of +/- FRAC_PI_2 to avoid flipping
+const MIN_DISTANCE: f32 = 2.0;
+const MAX_DISTANCE: f32 = 30.0;
+const ZOOM_SPEED: f32 = 1.0;
+const FOLLOW_SMOOTHING: f32 = 8.0;
+
+/// How quickly accumulated shake trauma (see `shake_trauma`) decays per
+/// second once g-force stops adding to it.
+const SHAKE_DECAY: f32 = 2.0;
+const SHAKE_MAGNITUDE: f32 = 0.4;
+
+/// An orbit/follow camera tracking `target`. Replaces the old child-camera
+/// approach so the pile of cubes can be observed from any angle.
+#[derive(Component)]
+pub struct FollowCamera {
+    pub target: Entity,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    /// 0..=1 shake intensity, raised by hard landings (see `gforce`) and
+    /// decayed back to zero here every frame.
+    pub shake_trauma: f32,
+}
+
+impl FollowCamera {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 10.0,
+            shake_trauma: 0.0,
+        }
+    }
+}
+
+/// Locks and hides the cursor so mouse motion can drive the camera instead
+/// of moving a visible OS pointer.
+pub fn grab_cursor(mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    window.cursor_options.grab_mode = CursorGrabMode::Locked;
+    window.cursor_options.visible = false;
+}
+
+/// Accumulates mouse-look yaw/pitch and scroll-wheel zoom, then lerps the
+/// camera toward `target.translation + offset` each frame.
+pub fn camera_controls(
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut camera_query: Query<(&mut Transform, &mut FollowCamera)>,
+    target_query: Query<&Transform, Without<FollowCamera>>,
+    time: Res<Time>,
+) {
+    let mut look_delta = Vec2::ZERO;
+    for motion in mouse_motion.read() {
+        look_delta += motion.delta;
+    }
+
+    let mut zoom_delta = 0.0;
+    for wheel in mouse_wheel.read() {
+        zoom_delta -= wheel.y;
+    }
+
+    let Ok((mut camera_transform, mut follow_camera)) = camera_query.get_single_mut() else {
+        return;
+    };
+    let Ok(target_transform) = target_query.get(follow_camera.target) else {
+        return;
+    };
+
+    follow_camera.yaw -= look_delta.x * MOUSE_SENSITIVITY;
+    follow_camera.pitch = (follow_camera.pitch - look_delta.y * MOUSE_SENSITIVITY)
+        .clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    follow_camera.distance =
+        (follow_camera.distance + zoom_delta * ZOOM_SPEED).clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+    let look_rotation = Quat::from_euler(EulerRot::YXZ, follow_camera.yaw, follow_camera.pitch, 0.0);
+    let offset = look_rotation * (Vec3::Z * follow_camera.distance);
+    let target_position = target_transform.translation + offset;
+
+    let dt = time.delta_secs();
+    let smoothing = 1.0 - (-FOLLOW_SMOOTHING * dt).exp();
+    camera_transform.translation = camera_transform.translation.lerp(target_position, smoothing);
+    camera_transform.look_at(target_transform.translation, Vec3::Y);
+
+    if follow_camera.shake_trauma > 0.0 {
+        let elapsed = time.elapsed_secs();
+        let shake = follow_camera.shake_trauma * follow_camera.shake_trauma * SHAKE_MAGNITUDE;
+        let jitter = Vec3::new(
+            (elapsed * 37.0).sin(),
+            (elapsed * 53.0).sin(),
+            (elapsed * 41.0).sin(),
+        ) * shake;
+        camera_transform.translation += jitter;
+        follow_camera.shake_trauma = (follow_camera.shake_trauma - SHAKE_DECAY * dt).max(0.0);
+    }
+}
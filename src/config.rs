@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+
+use crate::StressConfig;
+use crate::bench::BenchArgs;
+use crate::netcode::NetcodeArgs;
+
+#[derive(Copy, Clone, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Toggle {
+    On,
+    Off,
+}
+
+impl Toggle {
+    pub fn is_on(self) -> bool {
+        matches!(self, Toggle::On)
+    }
+}
+
+/// Every flag the stress test understands, including the GGRS (`--players`)
+/// and `--bench` flags, unified behind one `clap` parser so all of them can
+/// also come from `--config <file.toml>`.
+#[derive(Parser, Debug, Default)]
+#[command(name = "avian3d-stress-test1", about = "Avian3D cube-pile stress test")]
+pub struct Cli {
+    /// TOML file providing any of the flags below. CLI flags take priority
+    /// over the file when both are given.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    #[arg(long)]
+    pub cube_axis_amount: Option<i32>,
+    #[arg(long)]
+    pub physics_hz: Option<f64>,
+    #[arg(long)]
+    pub floor_radius: Option<f32>,
+    #[arg(long)]
+    pub movement_speed: Option<f32>,
+    #[arg(long)]
+    pub jump_speed: Option<f32>,
+    #[arg(long)]
+    pub ground_distance: Option<f32>,
+    #[arg(long)]
+    pub gravity_scale: Option<f32>,
+    #[arg(long)]
+    pub cube_mass: Option<f32>,
+    #[arg(long, value_enum)]
+    pub vsync: Option<Toggle>,
+    #[arg(long, value_enum)]
+    pub interpolation: Option<Toggle>,
+
+    #[arg(long)]
+    pub bench: bool,
+    #[arg(long, value_delimiter = ',')]
+    pub schedule: Option<Vec<i32>>,
+    #[arg(long)]
+    pub ticks_per_level: Option<u32>,
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    #[arg(long)]
+    pub local_port: Option<u16>,
+    #[arg(long, num_args = 0..)]
+    pub players: Vec<String>,
+    #[arg(long)]
+    pub sync_test: Option<usize>,
+}
+
+/// Mirrors [`Cli`]'s stress-tuning fields so a TOML file can set the same
+/// values without needing every GGRS/bench flag too.
+#[derive(Deserialize, Default)]
+struct FileConfig {
+    cube_axis_amount: Option<i32>,
+    physics_hz: Option<f64>,
+    floor_radius: Option<f32>,
+    movement_speed: Option<f32>,
+    jump_speed: Option<f32>,
+    ground_distance: Option<f32>,
+    gravity_scale: Option<f32>,
+    cube_mass: Option<f32>,
+    vsync: Option<Toggle>,
+    interpolation: Option<Toggle>,
+}
+
+impl Cli {
+    /// Parses `std::env::args`, then fills in anything left unset from
+    /// `--config <file.toml>` if one was given.
+    pub fn load() -> Self {
+        let mut cli = Cli::parse();
+
+        let Some(path) = &cli.config else {
+            return cli;
+        };
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("failed to read --config {}: {err}", path.display()));
+        let file: FileConfig = toml::from_str(&contents)
+            .unwrap_or_else(|err| panic!("failed to parse --config {}: {err}", path.display()));
+
+        cli.cube_axis_amount = cli.cube_axis_amount.or(file.cube_axis_amount);
+        cli.physics_hz = cli.physics_hz.or(file.physics_hz);
+        cli.floor_radius = cli.floor_radius.or(file.floor_radius);
+        cli.movement_speed = cli.movement_speed.or(file.movement_speed);
+        cli.jump_speed = cli.jump_speed.or(file.jump_speed);
+        cli.ground_distance = cli.ground_distance.or(file.ground_distance);
+        cli.gravity_scale = cli.gravity_scale.or(file.gravity_scale);
+        cli.cube_mass = cli.cube_mass.or(file.cube_mass);
+        cli.vsync = cli.vsync.or(file.vsync);
+        cli.interpolation = cli.interpolation.or(file.interpolation);
+
+        cli
+    }
+
+    pub fn stress_config(&self) -> StressConfig {
+        let defaults = StressConfig::default();
+        StressConfig {
+            cube_axis_amount: self.cube_axis_amount.unwrap_or(defaults.cube_axis_amount),
+            physics_hz: self.physics_hz.unwrap_or(defaults.physics_hz),
+            floor_radius: self.floor_radius.unwrap_or(defaults.floor_radius),
+            movement_speed: self.movement_speed.unwrap_or(defaults.movement_speed),
+            jump_speed: self.jump_speed.unwrap_or(defaults.jump_speed),
+            ground_distance: self.ground_distance.unwrap_or(defaults.ground_distance),
+            gravity_scale: self.gravity_scale.unwrap_or(defaults.gravity_scale),
+            cube_mass: self.cube_mass.unwrap_or(defaults.cube_mass),
+        }
+    }
+
+    pub fn vsync_on(&self) -> bool {
+        self.vsync.map(Toggle::is_on).unwrap_or(false)
+    }
+
+    pub fn interpolation_on(&self) -> bool {
+        self.interpolation.map(Toggle::is_on).unwrap_or(true)
+    }
+
+    pub fn bench_args(&self) -> Option<BenchArgs> {
+        BenchArgs::from_cli(self)
+    }
+
+    pub fn netcode_args(&self) -> Option<NetcodeArgs> {
+        NetcodeArgs::from_cli(self)
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::camera::FollowCamera;
+use crate::PlayerController;
+
+/// g-force magnitude above which landings start shaking the camera.
+const SHAKE_THRESHOLD: f32 = 2.0;
+const SHAKE_GAIN: f32 = 0.15;
+
+/// g-force magnitude above which the screen vignette starts tinting in.
+const VIGNETTE_THRESHOLD: f32 = 3.0;
+const VIGNETTE_GAIN: f32 = 0.2;
+
+/// g-force magnitude above which `movement_controls` is briefly disabled.
+const STUN_THRESHOLD: f32 = 6.0;
+const STUN_DURATION: f32 = 0.3;
+
+/// Instantaneous g-force derived from the player capsule's velocity change
+/// between physics ticks. Drives camera shake, the screen vignette, and the
+/// movement stun below.
+#[derive(Component, Debug, Default)]
+pub struct GForce {
+    pub magnitude: f32,
+}
+
+/// Full-screen overlay whose alpha is driven by `GForce` to read as a
+/// red-out on hard impacts.
+#[derive(Component)]
+pub struct Vignette;
+
+pub fn spawn_vignette(commands: &mut Commands) {
+    commands.spawn((
+        Vignette,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.6, 0.0, 0.0, 0.0)),
+        ZIndex(i32::MAX),
+    ));
+}
+
+/// Runs in `FixedPostUpdate` after `PhysicsSet::Sync` so `LinearVelocity`
+/// already reflects this tick's physics step.
+pub fn update_g_force(
+    time: Res<Time>,
+    mut player_query: Query<(&LinearVelocity, &mut PlayerController, &mut GForce)>,
+    mut camera_query: Query<&mut FollowCamera>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (linear_velocity, mut player_controller, mut g_force) in &mut player_query {
+        let acceleration = (linear_velocity.0 - player_controller.previous_velocity) / dt;
+        // 9.81 m/s^2 per g.
+        let magnitude = acceleration.length() / 9.81;
+        g_force.magnitude = magnitude;
+        player_controller.previous_velocity = linear_velocity.0;
+
+        if magnitude > SHAKE_THRESHOLD {
+            for mut follow_camera in &mut camera_query {
+                follow_camera.shake_trauma = (follow_camera.shake_trauma
+                    + (magnitude - SHAKE_THRESHOLD) * SHAKE_GAIN)
+                    .min(1.0);
+            }
+        }
+
+        if magnitude > STUN_THRESHOLD {
+            player_controller.stun_timer =
+                Timer::new(Duration::from_secs_f32(STUN_DURATION), TimerMode::Once);
+        }
+    }
+}
+
+pub fn update_vignette(
+    player_query: Query<&GForce, With<PlayerController>>,
+    mut vignette_query: Query<&mut BackgroundColor, With<Vignette>>,
+) {
+    let Ok(g_force) = player_query.get_single() else {
+        return;
+    };
+    let Ok(mut background_color) = vignette_query.get_single_mut() else {
+        return;
+    };
+
+    let alpha = ((g_force.magnitude - VIGNETTE_THRESHOLD) * VIGNETTE_GAIN).clamp(0.0, 0.6);
+    background_color.0.set_alpha(alpha);
+}
@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use avian3d::prelude::*;
 use bevy::{
-    asset::RenderAssetUsages,
+    asset::{AssetApp, RenderAssetUsages},
     color::palettes::css,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
@@ -11,21 +11,42 @@ use bevy::{
     winit::{UpdateMode, WinitSettings},
 };
 
-// Amount of cubes to spawn (^3)
-const CUBE_AXIS_AMOUNT:i32 = 10;
+mod bench;
+mod camera;
+mod config;
+mod gforce;
+mod netcode;
+mod skybox;
 
-// Physics tick rate
+use bench::{drive_bench, BenchController};
+use camera::{camera_controls, grab_cursor, FollowCamera};
+use config::Cli;
+use gforce::{update_g_force, update_vignette, GForce};
+use netcode::{advance_netcode_frame, apply_confirmed_input, ConfirmedInput, NetcodeTick};
+
+// Amount of cubes to spawn (^3). Default for `StressConfig`; override with
+// `--cube-axis-amount` or a `--config` TOML file.
+const CUBE_AXIS_AMOUNT: i32 = 10;
+
+// Physics tick rate. Default for `StressConfig`; override with `--physics-hz`.
 const PHYSICS_HZ: f64 = 60.0;
 
-// Environment
+// Environment. Default for `StressConfig`; override with `--floor-radius`.
 const FLOOR_RADIUS: f32 = 100.0;
 
-// Player Controller
+// Player Controller. Defaults for `StressConfig`; override with
+// `--movement-speed`, `--jump-speed`, `--ground-distance`, `--gravity-scale`.
 const MOVEMENT_SPEED: f32 = 10.;
-const ROTATE_SPEED: f32 = 0.05;
+// Read by `netcode::apply_confirmed_input` too, since it replays the same
+// rotation logic as `movement_controls` from packed `StressInput` bits.
+pub(crate) const ROTATE_SPEED: f32 = 0.05;
 const JUMP_SPEED: f32 = 75.0;
 const GROUND_DISTANCE: f32 = 1.01;
 const JUMP_COOLDOWN: f32 = 0.1;
+const GRAVITY_SCALE: f32 = 2.0;
+
+// Cube tuning. Default for `StressConfig`; override with `--cube-mass`.
+const CUBE_MASS: f32 = 10.0;
 
 
 #[derive(Component, Debug)]
@@ -42,33 +63,168 @@ impl Velocity {
 #[derive(Component, Debug)]
 pub struct PlayerController {
     pub velocity: Velocity,
+    pub previous_velocity: Vec3,
     pub jump_timer: Timer,
+    /// While not finished, `movement_controls` ignores input. Set by
+    /// `gforce::update_g_force` when the player slams into the cube pile.
+    pub stun_timer: Timer,
     pub is_on_ground: bool,
 }
 
+/// Marks one of the spawned stress-test cubes so `--bench` can despawn and
+/// respawn the pile when it ramps the cube count.
+#[derive(Component)]
+pub struct Cube;
+
+/// Every stress-test tuning value that used to be a hardcoded `const`.
+/// `setup`, `movement_controls` and `check_is_on_ground` read this instead,
+/// so a run can be reproduced from the command line or a `--config` file
+/// without recompiling.
+#[derive(Resource, Clone)]
+pub struct StressConfig {
+    pub cube_axis_amount: i32,
+    pub physics_hz: f64,
+    pub floor_radius: f32,
+    pub movement_speed: f32,
+    pub jump_speed: f32,
+    pub ground_distance: f32,
+    pub gravity_scale: f32,
+    pub cube_mass: f32,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            cube_axis_amount: CUBE_AXIS_AMOUNT,
+            physics_hz: PHYSICS_HZ,
+            floor_radius: FLOOR_RADIUS,
+            movement_speed: MOVEMENT_SPEED,
+            jump_speed: JUMP_SPEED,
+            ground_distance: GROUND_DISTANCE,
+            gravity_scale: GRAVITY_SCALE,
+            cube_mass: CUBE_MASS,
+        }
+    }
+}
+
 fn main() {
-    App::new()
-        .insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ))
+    let cli = Cli::load();
+    let stress_config = cli.stress_config();
+    // Captured before `stress_config` is moved into a resource below, so
+    // `NetcodeArgs::build_session` can pin GGRS's pacing assumption to the
+    // same rate `Time::<Fixed>` actually runs at.
+    let physics_hz = stress_config.physics_hz;
+    let bench_args = cli.bench_args();
+    let netcode_args = cli.netcode_args();
+
+    let mut app = App::new();
+    app.insert_resource(Time::<Fixed>::from_hz(stress_config.physics_hz))
         .insert_resource(WinitSettings {
             focused_mode: UpdateMode::Continuous,
             unfocused_mode: UpdateMode::Continuous,
         })
-        .add_plugins((
+        .add_plugins(FrameTimeDiagnosticsPlugin);
+
+    // `--bench` runs headless (no window, no renderer) so it can execute in
+    // CI: just the physics/diagnostics plugins driven at a fixed tick rate.
+    if let Some(bench_args) = &bench_args {
+        app.add_plugins((
+            // `Duration::ZERO` means "run the schedule as fast as possible":
+            // capping it at the physics tick rate would pad every frame-time
+            // sample with the runner's sleep and make the bench's min-FPS
+            // meaningless.
+            MinimalPlugins.set(bevy::app::ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            AssetPlugin::default(),
+        ));
+        // `setup` and `spawn_cube_pile` need `Assets<Mesh>`/`Assets<Image>`/
+        // `Assets<StandardMaterial>`, which only `DefaultPlugins`' render/pbr
+        // plugins register normally. Register just the collections headless
+        // mode needs instead of pulling in the renderer.
+        app.init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<StandardMaterial>();
+        app.insert_resource(StressConfig {
+            cube_axis_amount: bench_args.schedule[0],
+            ..stress_config
+        });
+        app.insert_resource(BenchController::new(
+            bench_args.schedule.clone(),
+            bench_args.ticks_per_level,
+            bench_args.out_path.clone(),
+        ));
+        app.insert_resource(bench::PhysicsStepTiming::default()).add_systems(
+            FixedPostUpdate,
+            (
+                bench::begin_physics_step_timing.before(PhysicsSet::Prepare),
+                bench::end_physics_step_timing.after(PhysicsSet::Sync),
+                drive_bench.after(bench::end_physics_step_timing),
+            ),
+        );
+    } else {
+        let present_mode = if cli.vsync_on() {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+        app.add_plugins((
             LogDiagnosticsPlugin::default(),
-            FrameTimeDiagnosticsPlugin,
             DefaultPlugins
                 .set(WindowPlugin {
                     primary_window: Some(Window {
-                        present_mode: PresentMode::AutoNoVsync,
+                        present_mode,
                         ..default()
                     }),
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest()),
-        ))
-        .add_plugins(PhysicsPlugins::default().set(PhysicsInterpolationPlugin::interpolate_all()))
-        .add_systems(Startup, setup)
-        .add_systems(
+        ));
+        app.insert_resource(stress_config);
+    }
+
+    // Stress-testing determinism across machines: drive physics from GGRS's
+    // confirmed/predicted frames instead of wall-clock time. When active,
+    // Avian has to run in `NetcodeTick` rather than its usual
+    // `FixedPostUpdate` slot, since `advance_netcode_frame` may run that
+    // schedule zero, one, or several times in a single real frame while
+    // resimulating a rollback.
+    let netcode_active = netcode_args.is_some();
+    // `--bench` runs under `MinimalPlugins`: no keyboard/mouse resources and
+    // no renderer, so the interactive-only systems below must stay out of
+    // its schedule entirely rather than merely no-op at runtime.
+    let headless = bench_args.is_some();
+
+    let physics_plugins = match (netcode_active, cli.interpolation_on()) {
+        (true, _) => PhysicsPlugins::new(NetcodeTick),
+        (false, true) => PhysicsPlugins::default().set(PhysicsInterpolationPlugin::interpolate_all()),
+        (false, false) => PhysicsPlugins::default(),
+    };
+    app.add_plugins(physics_plugins)
+        .add_systems(Startup, (setup, grab_cursor));
+
+    if netcode_active {
+        app.add_systems(
+            NetcodeTick,
+            (
+                check_is_on_ground,
+                apply_confirmed_input,
+                update_linear_velocity,
+                apply_impulses,
+            )
+                .chain()
+                .before(PhysicsSet::Prepare),
+        )
+        .add_systems(NetcodeTick, update_g_force.after(PhysicsSet::Sync))
+        // Paced by `Time::<Fixed>`'s accumulator rather than the unlocked
+        // `Update`/render loop, so GGRS is fed at exactly `physics_hz`.
+        .add_systems(FixedUpdate, advance_netcode_frame);
+    } else if headless {
+        app.add_systems(
+            FixedPreUpdate,
+            (check_is_on_ground, update_linear_velocity, apply_impulses).chain(),
+        )
+        .add_systems(FixedPostUpdate, update_g_force.after(PhysicsSet::Sync));
+    } else {
+        app.add_systems(
             FixedPreUpdate,
             (
                 check_is_on_ground,
@@ -78,7 +234,23 @@ fn main() {
             )
                 .chain(),
         )
-        .run();
+        .add_systems(FixedPostUpdate, update_g_force.after(PhysicsSet::Sync));
+    }
+
+    if !headless {
+        app.add_systems(
+            Update,
+            (camera_controls, skybox::asset_loaded, update_vignette),
+        );
+    }
+
+    if let Some(netcode_args) = netcode_args {
+        let session = netcode_args.build_session(0, physics_hz);
+        app.insert_resource(session)
+            .insert_resource(ConfirmedInput::default());
+    }
+
+    app.run();
 }
 
 fn setup(
@@ -86,6 +258,9 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut images: ResMut<Assets<Image>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    stress_config: Res<StressConfig>,
+    bench_controller: Option<Res<BenchController>>,
 ) {
     let capsule_radius = 0.5;
     let capsule_half_length = 0.5;
@@ -104,9 +279,9 @@ fn setup(
     // Floor
     commands.spawn((
         RigidBody::Static,
-        Collider::cylinder(FLOOR_RADIUS, 0.1),
+        Collider::cylinder(stress_config.floor_radius, 0.1),
         (
-            Mesh3d(meshes.add(Cylinder::new(FLOOR_RADIUS, 0.1))),
+            Mesh3d(meshes.add(Cylinder::new(stress_config.floor_radius, 0.1))),
             MeshMaterial3d(debug_material.clone()),
         ),
         Friction::new(1.0),
@@ -125,39 +300,15 @@ fn setup(
         Transform::from_xyz(light_distance, light_distance, -light_distance).looking_at(-Vec3::Y, Vec3::Z),
     ));
 
-    // let color_step = 1.0 / CUBE_AXIS_AMOUNT as f32;
+    // let color_step = 1.0 / stress_config.cube_axis_amount as f32;
 
-    // Cubes
-    for i in 0..CUBE_AXIS_AMOUNT {
-        for j in 0..CUBE_AXIS_AMOUNT {
-            for k in 0..CUBE_AXIS_AMOUNT {
-                commands.spawn((
-                    Mesh3d(meshes.add(Cuboid {
-                        half_size: Vec3::new(cube_half_size, cube_half_size, cube_half_size),
-                    })),
-                    MeshMaterial3d(materials.add(Color::from(
-                        css::SKY_BLUE
-
-                    //     Srgba {
-                    //     red: i as f32 * color_step,
-                    //     green: j as f32 * color_step,
-                    //     blue: k as f32 * color_step,
-                    //     alpha: 1.0,
-                    // }
-                ))),
-                    Transform::from_xyz(
-                        i as f32 + cube_half_size - (CUBE_AXIS_AMOUNT as f32 / 2.0),
-                        j as f32 + starting_position_offset,
-                        k as f32 + starting_position_offset / 2.0,
-                    ),
-                    RigidBody::Dynamic,
-                    Mass(10.0),
-                    Friction::new(0.9),
-                    Collider::cuboid(cube_size, cube_size, cube_size),
-                ));
-            }
-        }
-    }
+    spawn_cube_pile(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        stress_config.cube_axis_amount,
+        stress_config.cube_mass,
+    );
 
     // Capsule
     let player = commands
@@ -171,26 +322,39 @@ fn setup(
             RigidBody::Dynamic,
             Collider::capsule(capsule_radius, capsule_length),
             Mass(10.),
-            GravityScale(2.0),
+            GravityScale(stress_config.gravity_scale),
             ExternalImpulse::default(),
             LockedAxes::ROTATION_LOCKED,
             PlayerController {
                 velocity: Velocity::new(Vec3::ZERO),
+                previous_velocity: Vec3::ZERO,
                 jump_timer: Timer::new(Duration::from_secs_f32(JUMP_COOLDOWN), TimerMode::Once),
+                stun_timer: Timer::new(Duration::ZERO, TimerMode::Once),
                 is_on_ground: false,
             },
+            GForce::default(),
         ))
         .id();
 
     // Spawn Player's Children
 
-    // Camera
-    let child_camera = commands
+    // Follow Camera. Not a child: it tracks the player in world space
+    // instead of inheriting the player's rotation.
+    let camera = commands
         .spawn((
             Camera3d::default(),
-            Transform::from_xyz(0., 2.0, -10.0).looking_at(Vec3{x: 0.0, y: 1.0, z: 0.0}, Dir3::Y),
+            Transform::from_xyz(0.0, 2.0, -10.0).looking_at(Vec3::ZERO, Dir3::Y),
+            FollowCamera::new(player),
         ))
         .id();
+    // `--bench` runs headless under `MinimalPlugins`: there's no renderer to
+    // show a skybox on, and no `assets/` directory is shipped for `--bench`
+    // to load it from either, so skip it rather than stalling on a load that
+    // will never finish.
+    if bench_controller.is_none() {
+        skybox::attach_skybox(&mut commands, &asset_server, camera);
+    }
+    gforce::spawn_vignette(&mut commands);
 
     // Pointer Cone
     let child_cone = commands
@@ -213,7 +377,44 @@ fn setup(
     //Add Children to Player
     commands
         .entity(player)
-        .add_children(&[child_camera, child_cone, child_raycaster]);
+        .add_children(&[child_cone, child_raycaster]);
+}
+
+/// Spawns a `cube_axis_amount^3` cube of dynamic rigid bodies, tagged with
+/// `Cube` so `--bench` can despawn and respawn the pile between levels.
+pub(crate) fn spawn_cube_pile(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    cube_axis_amount: i32,
+    cube_mass: f32,
+) {
+    let cube_half_size = 0.4;
+    let cube_size = cube_half_size * 2.0;
+    let starting_position_offset = 10.0;
+
+    for i in 0..cube_axis_amount {
+        for j in 0..cube_axis_amount {
+            for k in 0..cube_axis_amount {
+                commands.spawn((
+                    Cube,
+                    Mesh3d(meshes.add(Cuboid {
+                        half_size: Vec3::new(cube_half_size, cube_half_size, cube_half_size),
+                    })),
+                    MeshMaterial3d(materials.add(Color::from(css::SKY_BLUE))),
+                    Transform::from_xyz(
+                        i as f32 + cube_half_size - (cube_axis_amount as f32 / 2.0),
+                        j as f32 + starting_position_offset,
+                        k as f32 + starting_position_offset / 2.0,
+                    ),
+                    RigidBody::Dynamic,
+                    Mass(cube_mass),
+                    Friction::new(0.9),
+                    Collider::cuboid(cube_size, cube_size, cube_size),
+                ));
+            }
+        }
+    }
 }
 
 fn uv_debug_texture() -> Image {
@@ -248,6 +449,7 @@ fn movement_controls(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut query: Query<(&mut Transform, &mut PlayerController), With<PlayerController>>,
     time: Res<Time>,
+    stress_config: Res<StressConfig>,
 ) {
     let mut forward_movement = 0.0;
     let mut side_movement = 0.0;
@@ -263,6 +465,14 @@ fn movement_controls(
         .jump_timer
         .tick(Duration::from_secs_f32(time.delta_secs()));
 
+    if !player_controller
+        .stun_timer
+        .tick(Duration::from_secs_f32(time.delta_secs()))
+        .finished()
+    {
+        return;
+    }
+
     // TODO: Limit controls while airborne
     if keyboard.pressed(KeyCode::KeyW) {
         forward_movement = 1.0;
@@ -294,11 +504,11 @@ fn movement_controls(
     // Normalize horizontal movement
     h_vel = (-transform.forward() * forward_movement) + (-transform.left() * side_movement);
     if !is_near_zero(forward_movement) || !is_near_zero(side_movement) {
-        h_vel = MOVEMENT_SPEED * h_vel.normalize();
+        h_vel = stress_config.movement_speed * h_vel.normalize();
     }
 
     player_controller.velocity.value = h_vel;
-    player_controller.velocity.value.y = upward_movement * JUMP_SPEED;
+    player_controller.velocity.value.y = upward_movement * stress_config.jump_speed;
     // println!("{:?}", vel);
     // println!("{:?}", player_controller.jump_timer.remaining());
 }
@@ -329,6 +539,7 @@ fn is_near_zero(value: f32) -> bool {
 fn check_is_on_ground(
     mut player_query: Query<&mut PlayerController>,
     mut ray_query: Query<&RayHits>,
+    stress_config: Res<StressConfig>,
 ) {
     let Ok(mut player_controller) = player_query.get_single_mut() else {
         println!("Could not query!");
@@ -343,7 +554,7 @@ fn check_is_on_ground(
             // println!("Hit entity {} at distance {}", hit.entity, hit.distance,);
 
             // Only check first ray hit
-            player_controller.is_on_ground = hit.distance <= GROUND_DISTANCE;
+            player_controller.is_on_ground = hit.distance <= stress_config.ground_distance;
             // println!("{:?}", player_controller.is_on_ground);
             return;
         }
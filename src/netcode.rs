@@ -0,0 +1,430 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+use bytemuck::{Pod, Zeroable};
+use ggrs::{Config, P2PSession, PlayerHandle, SessionBuilder, SyncTestSession};
+
+use crate::config::Cli;
+use crate::{PlayerController, StressConfig, ROTATE_SPEED};
+
+const INPUT_FORWARD: u8 = 1 << 0;
+const INPUT_BACK: u8 = 1 << 1;
+const INPUT_LEFT: u8 = 1 << 2;
+const INPUT_RIGHT: u8 = 1 << 3;
+const INPUT_ROTATE_LEFT: u8 = 1 << 4;
+const INPUT_ROTATE_RIGHT: u8 = 1 << 5;
+const INPUT_JUMP: u8 = 1 << 6;
+
+/// The schedule GGRS drives directly: `advance_netcode_frame` calls
+/// `World::run_schedule(NetcodeTick)` exactly once per confirmed/predicted
+/// `GgrsRequest::AdvanceFrame`, which may happen several times in a row
+/// while resimulating a rollback, or not at all while waiting on a remote
+/// player. Avian's physics plugins are configured to run here instead of
+/// their usual `FixedPostUpdate` slot so the solver only ever advances on
+/// GGRS's say-so, never on wall-clock time.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NetcodeTick;
+
+/// A single player's `movement_controls` input packed into one byte so it is
+/// cheap to serialize and send every rollback frame.
+#[repr(C)]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct StressInput {
+    pub buttons: u8,
+}
+
+impl StressInput {
+    pub fn from_keyboard(keyboard: &ButtonInput<KeyCode>) -> Self {
+        let mut buttons = 0;
+        if keyboard.pressed(KeyCode::KeyW) {
+            buttons |= INPUT_FORWARD;
+        }
+        if keyboard.pressed(KeyCode::KeyS) {
+            buttons |= INPUT_BACK;
+        }
+        if keyboard.pressed(KeyCode::KeyQ) {
+            buttons |= INPUT_LEFT;
+        }
+        if keyboard.pressed(KeyCode::KeyE) {
+            buttons |= INPUT_RIGHT;
+        }
+        if keyboard.pressed(KeyCode::KeyA) {
+            buttons |= INPUT_ROTATE_LEFT;
+        }
+        if keyboard.pressed(KeyCode::KeyD) {
+            buttons |= INPUT_ROTATE_RIGHT;
+        }
+        if keyboard.pressed(KeyCode::Space) {
+            buttons |= INPUT_JUMP;
+        }
+        Self { buttons }
+    }
+
+    pub fn pressed(&self, mask: u8) -> bool {
+        self.buttons & mask != 0
+    }
+}
+
+/// The confirmed/predicted input for the frame currently being (re)simulated.
+/// Inserted by `advance_netcode_frame` right before running `NetcodeTick`, so
+/// `apply_confirmed_input` sees exactly the bits GGRS wants this tick instead
+/// of reading `ButtonInput<KeyCode>` directly.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct ConfirmedInput(pub StressInput);
+
+/// A snapshot of every dynamic rigid body's physics state plus the player's
+/// own `PlayerController` fields, used for GGRS's save/load callbacks and
+/// for the `SyncTest` checksum comparison. `PlayerController` isn't a
+/// physics component Avian knows about, so it's captured separately from
+/// `bodies`; skipping it would let `previous_velocity`/`jump_timer`/
+/// `stun_timer` stay at their pre-rollback values across a resimulation,
+/// desyncing gforce/shake/stun from the rest of the replayed frame even
+/// though the checksum (which only hashes `bodies`) wouldn't catch it.
+#[derive(Clone, Default)]
+pub struct WorldSnapshot {
+    bodies: Vec<BodyState>,
+    player: Option<PlayerControllerState>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct BodyState {
+    entity: Entity,
+    translation: Vec3,
+    rotation: Quat,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    sleeping: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct PlayerControllerState {
+    entity: Entity,
+    previous_velocity: Vec3,
+    jump_timer_elapsed: Duration,
+    stun_timer_elapsed: Duration,
+}
+
+impl WorldSnapshot {
+    pub fn capture(world: &mut World) -> Self {
+        let mut query = world.query::<(
+            Entity,
+            &Transform,
+            &LinearVelocity,
+            &AngularVelocity,
+            Option<&Sleeping>,
+        )>();
+
+        let mut bodies: Vec<BodyState> = query
+            .iter(world)
+            .map(
+                |(entity, transform, linear_velocity, angular_velocity, sleeping)| BodyState {
+                    entity,
+                    translation: transform.translation,
+                    rotation: transform.rotation,
+                    linear_velocity: linear_velocity.0,
+                    angular_velocity: angular_velocity.0,
+                    sleeping: sleeping.is_some(),
+                },
+            )
+            .collect();
+        // Sort so the checksum is independent of query iteration order.
+        bodies.sort_by_key(|body| body.entity);
+
+        let mut player_query = world.query::<(Entity, &PlayerController)>();
+        let player = player_query
+            .iter(world)
+            .next()
+            .map(|(entity, controller)| PlayerControllerState {
+                entity,
+                previous_velocity: controller.previous_velocity,
+                jump_timer_elapsed: controller.jump_timer.elapsed(),
+                stun_timer_elapsed: controller.stun_timer.elapsed(),
+            });
+
+        Self { bodies, player }
+    }
+
+    pub fn restore(&self, world: &mut World) {
+        for body in &self.bodies {
+            let Ok(mut entity_mut) = world.get_entity_mut(body.entity) else {
+                continue;
+            };
+            if let Some(mut transform) = entity_mut.get_mut::<Transform>() {
+                transform.translation = body.translation;
+                transform.rotation = body.rotation;
+            }
+            if let Some(mut linear_velocity) = entity_mut.get_mut::<LinearVelocity>() {
+                linear_velocity.0 = body.linear_velocity;
+            }
+            if let Some(mut angular_velocity) = entity_mut.get_mut::<AngularVelocity>() {
+                angular_velocity.0 = body.angular_velocity;
+            }
+            if body.sleeping {
+                entity_mut.insert(Sleeping);
+            } else {
+                entity_mut.remove::<Sleeping>();
+            }
+        }
+
+        if let Some(player) = &self.player {
+            if let Ok(mut entity_mut) = world.get_entity_mut(player.entity) {
+                if let Some(mut controller) = entity_mut.get_mut::<PlayerController>() {
+                    controller.previous_velocity = player.previous_velocity;
+                    controller.jump_timer.set_elapsed(player.jump_timer_elapsed);
+                    controller.stun_timer.set_elapsed(player.stun_timer_elapsed);
+                }
+            }
+        }
+    }
+
+    /// A cheap fletcher-like checksum over every transform and the player
+    /// controller's own rollback-relevant fields, used by `SyncTestSession`
+    /// to confirm resimulation is bit-identical.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for body in &self.bodies {
+            for component in body
+                .translation
+                .to_array()
+                .into_iter()
+                .chain(body.rotation.to_array())
+                .chain(body.linear_velocity.to_array())
+                .chain(body.angular_velocity.to_array())
+            {
+                hash ^= component.to_bits() as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        if let Some(player) = &self.player {
+            for component in player
+                .previous_velocity
+                .to_array()
+                .into_iter()
+                .chain([
+                    player.jump_timer_elapsed.as_secs_f32(),
+                    player.stun_timer_elapsed.as_secs_f32(),
+                ])
+            {
+                hash ^= component.to_bits() as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+}
+
+/// GGRS's `Config` for this app. Named distinctly from the crate's
+/// `StressConfig` resource so the two aren't confused.
+pub struct GgrsConfig;
+
+impl Config for GgrsConfig {
+    type Input = StressInput;
+    type State = WorldSnapshot;
+    type Address = SocketAddr;
+}
+
+/// Which kind of GGRS session the `--players` CLI flags requested.
+#[derive(Resource)]
+pub enum NetcodeSession {
+    P2P(P2PSession<GgrsConfig>),
+    SyncTest(SyncTestSession<GgrsConfig>),
+}
+
+impl NetcodeSession {
+    fn is_sync_test(&self) -> bool {
+        matches!(self, NetcodeSession::SyncTest(_))
+    }
+}
+
+/// Parsed from `--local-port <port> --players <addr>...`, mirroring the GGRS
+/// tank example's argument shape.
+pub struct NetcodeArgs {
+    pub local_port: u16,
+    pub players: Vec<String>,
+    pub sync_test_checks: Option<usize>,
+}
+
+impl NetcodeArgs {
+    /// Returns `None` when no netcode flags were passed, so `main` can fall
+    /// back to the normal wall-clock `FixedUpdate` loop.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.players.is_empty() && cli.sync_test.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            local_port: cli.local_port.unwrap_or(7000),
+            players: cli.players.clone(),
+            sync_test_checks: cli.sync_test,
+        })
+    }
+
+    /// `physics_hz` is `StressConfig::physics_hz`, the rate `Time::<Fixed>`
+    /// (and so `advance_netcode_frame`) actually paces at. It's threaded
+    /// through to `with_fps` so GGRS's own frame-advantage/rollback-window
+    /// math assumes the same rate the app really runs at, rather than
+    /// silently assuming a fixed default that could diverge from a
+    /// user-configured `--physics-hz`.
+    pub fn build_session(&self, local_handle: PlayerHandle, physics_hz: f64) -> NetcodeSession {
+        let fps = physics_hz.round() as usize;
+        if let Some(num_players) = self.sync_test_checks {
+            let session = SessionBuilder::<GgrsConfig>::new()
+                .with_num_players(self.players.len().max(1))
+                .with_check_distance(num_players)
+                .with_fps(fps)
+                .expect("invalid --physics-hz for GGRS SyncTestSession")
+                .start_synctest_session()
+                .expect("failed to start GGRS SyncTestSession");
+            return NetcodeSession::SyncTest(session);
+        }
+
+        let mut builder = SessionBuilder::<GgrsConfig>::new()
+            .with_num_players(self.players.len())
+            .with_fps(fps)
+            .expect("invalid --physics-hz for GGRS P2PSession");
+        for (handle, addr) in self.players.iter().enumerate() {
+            builder = if handle == local_handle {
+                builder
+                    .add_player(ggrs::PlayerType::Local, handle)
+                    .expect("failed to add local player")
+            } else {
+                let socket_addr: SocketAddr = addr.parse().expect("invalid --players address");
+                builder
+                    .add_player(ggrs::PlayerType::Remote(socket_addr), handle)
+                    .expect("failed to add remote player")
+            };
+        }
+
+        let socket = ggrs::UdpNonBlockingSocket::bind_to_port(self.local_port)
+            .expect("failed to bind local UDP socket");
+        NetcodeSession::P2P(
+            builder
+                .start_p2p_session(socket)
+                .expect("failed to start GGRS P2PSession"),
+        )
+    }
+}
+
+/// Drives the GGRS session as an exclusive system so `NetcodeTick` can be run
+/// zero or more times per real frame: once per `GgrsRequest::AdvanceFrame`,
+/// which is how GGRS asks for a rollback to be resimulated one tick at a
+/// time rather than all at once. Scheduled in `FixedUpdate` so GGRS is fed
+/// at exactly `StressConfig::physics_hz` (the same rate passed to
+/// `NetcodeArgs::build_session`'s `with_fps`), not once per unlocked
+/// `Update` frame.
+pub fn advance_netcode_frame(world: &mut World) {
+    let local_input = StressInput::from_keyboard(world.resource::<ButtonInput<KeyCode>>());
+
+    let is_sync_test = world.resource::<NetcodeSession>().is_sync_test();
+    let requests = {
+        let mut session = world.resource_mut::<NetcodeSession>();
+        let result = match &mut *session {
+            NetcodeSession::P2P(s) => {
+                s.poll_remote_clients();
+                s.local_player_handles()
+                    .first()
+                    .copied()
+                    .map(|handle| s.advance_frame(handle, &local_input))
+            }
+            NetcodeSession::SyncTest(s) => Some(s.advance_frame(0, &local_input)),
+        };
+        match result {
+            Some(Ok(requests)) => requests,
+            Some(Err(err)) => {
+                if is_sync_test {
+                    // This is the whole point of the SyncTest session: GGRS
+                    // reran the last `check_distance` frames from a restored
+                    // snapshot and the checksums it saved didn't match,
+                    // meaning the solver is nondeterministic under this load.
+                    panic!(
+                        "SyncTest detected nondeterministic physics: resimulated checksums diverged ({err:?})"
+                    );
+                }
+                return;
+            }
+            None => return,
+        }
+    };
+
+    for request in requests {
+        match request {
+            ggrs::GgrsRequest::SaveGameState { cell, frame } => {
+                let snapshot = WorldSnapshot::capture(world);
+                let checksum = snapshot.checksum();
+                cell.save(frame, Some(snapshot), Some(checksum as u128));
+            }
+            ggrs::GgrsRequest::LoadGameState { cell, .. } => {
+                if let Some(snapshot) = cell.load() {
+                    snapshot.restore(world);
+                }
+            }
+            ggrs::GgrsRequest::AdvanceFrame { inputs } => {
+                let confirmed_input = inputs.first().map(|(input, _status)| *input).unwrap_or_default();
+                world.insert_resource(ConfirmedInput(confirmed_input));
+                world.run_schedule(NetcodeTick);
+            }
+        }
+    }
+}
+
+/// `NetcodeTick`'s replacement for `movement_controls`: applies the
+/// confirmed/predicted `ConfirmedInput` for this rollback frame instead of
+/// reading the keyboard directly, so remote and resimulated frames drive the
+/// player exactly like the original local input did.
+pub fn apply_confirmed_input(
+    confirmed_input: Res<ConfirmedInput>,
+    stress_config: Res<StressConfig>,
+    mut query: Query<(&mut Transform, &mut PlayerController)>,
+) {
+    let Ok((mut transform, mut player_controller)) = query.get_single_mut() else {
+        return;
+    };
+
+    let dt = Duration::from_secs_f64(1.0 / stress_config.physics_hz);
+    player_controller.jump_timer.tick(dt);
+    if !player_controller.stun_timer.tick(dt).finished() {
+        return;
+    }
+
+    let input = confirmed_input.0;
+    let mut forward_movement = 0.0;
+    let mut side_movement = 0.0;
+    let mut upward_movement = 0.0;
+
+    if input.pressed(INPUT_FORWARD) {
+        forward_movement = 1.0;
+    }
+    if input.pressed(INPUT_BACK) {
+        forward_movement = -1.0;
+    }
+    if input.pressed(INPUT_LEFT) {
+        side_movement = 1.0;
+    }
+    if input.pressed(INPUT_RIGHT) {
+        side_movement = -1.0;
+    }
+    if input.pressed(INPUT_ROTATE_LEFT) {
+        transform.rotate_y(ROTATE_SPEED);
+    }
+    if input.pressed(INPUT_ROTATE_RIGHT) {
+        transform.rotate_y(-ROTATE_SPEED);
+    }
+    if input.pressed(INPUT_JUMP)
+        && !(player_controller.jump_timer.remaining() > Duration::ZERO)
+        && player_controller.is_on_ground
+    {
+        upward_movement = 1.0;
+        player_controller.jump_timer.reset();
+    }
+
+    let mut h_vel = (-transform.forward() * forward_movement) + (-transform.left() * side_movement);
+    if forward_movement != 0.0 || side_movement != 0.0 {
+        h_vel = stress_config.movement_speed * h_vel.normalize();
+    }
+
+    player_controller.velocity.value = h_vel;
+    player_controller.velocity.value.y = upward_movement * stress_config.jump_speed;
+}
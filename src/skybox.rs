@@ -0,0 +1,76 @@
+use bevy::asset::LoadState;
+use bevy::core_pipeline::Skybox;
+use bevy::prelude::*;
+use bevy::render::render_resource::{TextureViewDescriptor, TextureViewDimension};
+
+// Not shipped in this repo: drop a RGB9E5, zstd-compressed `.ktx2` cubemap
+// at `assets/environment_maps/pile_skybox_rgb9e5_zstd.ktx2` (e.g. baked with
+// `KTX-Software`'s `toktx`) to enable the skybox. Until then `asset_loaded`
+// simply never sees `LoadState::Loaded` and the camera falls back to no
+// skybox/environment lighting.
+const CUBEMAP_PATH: &str = "environment_maps/pile_skybox_rgb9e5_zstd.ktx2";
+
+/// Tracks the cubemap handle until it finishes loading, at which point
+/// `asset_loaded` reinterprets it as a cube texture.
+#[derive(Resource)]
+pub struct Cubemap {
+    pub image: Handle<Image>,
+    pub is_loaded: bool,
+}
+
+/// Starts loading the skybox cubemap and attaches `Skybox` + `EnvironmentMapLight`
+/// to the camera. Both reuse the same handle: this stress test only needs a
+/// plausible sky reflection on the cube pile, not physically separate
+/// diffuse/specular irradiance maps.
+pub fn attach_skybox(commands: &mut Commands, asset_server: &AssetServer, camera: Entity) {
+    let image: Handle<Image> = asset_server.load(CUBEMAP_PATH);
+    commands.insert_resource(Cubemap {
+        image: image.clone(),
+        is_loaded: false,
+    });
+    commands.entity(camera).insert((
+        Skybox {
+            image: image.clone(),
+            brightness: 1000.0,
+            ..default()
+        },
+        EnvironmentMapLight {
+            diffuse_map: image.clone(),
+            specular_map: image,
+            intensity: 900.0,
+            ..default()
+        },
+    ));
+}
+
+/// Polls the cubemap's `LoadState` and, once loaded, reinterprets it as a
+/// `TextureViewDimension::Cube` so the renderer samples it as a skybox
+/// rather than a flat stacked 2D image. `Cubemap` doesn't exist at all in
+/// `--bench`, which skips `attach_skybox`, so this no-ops rather than
+/// panicking on a missing resource if it's ever scheduled there.
+pub fn asset_loaded(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    cubemap: Option<ResMut<Cubemap>>,
+) {
+    let Some(mut cubemap) = cubemap else {
+        return;
+    };
+    if cubemap.is_loaded {
+        return;
+    }
+    if asset_server.load_state(&cubemap.image) != LoadState::Loaded {
+        return;
+    }
+
+    let image = images.get_mut(&cubemap.image).unwrap();
+    if image.texture_descriptor.array_layer_count() == 1 {
+        image.reinterpret_stacked_2d_as_array(image.height() / image.width());
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            ..default()
+        });
+    }
+
+    cubemap.is_loaded = true;
+}